@@ -7,15 +7,25 @@ use crate::{
     utils::RuntimeOrHandle,
 };
 use alloy_dyn_abi::DynSolValue;
-use alloy_primitives::{Bytes, B256, U256};
+use alloy_primitives::{keccak256, Bytes, B256, U256};
 use alloy_sol_types::sol;
 use ethers::{providers::Middleware, types::Filter};
-use foundry_abi::hevm::{EthGetLogsCall, RpcCall};
+use foundry_abi::hevm::{
+    EthGetLogs0Call, EthGetLogs1Call, EthGetLogs2Call, Rpc0Call, Rpc1Call, Rpc2Call, Rpc3Call,
+};
 use foundry_common::ProviderBuilder;
+use foundry_config::Config;
 use foundry_utils::types::{ToAlloy, ToEthers};
 use itertools::Itertools;
 use revm::EVMData;
+use serde::{de::DeserializeOwned, Serialize};
 use serde_json::Value;
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
 fn empty<T>(_: T) -> Bytes {
     Bytes::new()
@@ -186,8 +196,13 @@ pub fn apply<DB: DatabaseExt>(
             )
             .map(empty)
             .map_err(Into::into),
-        HEVMCalls::EthGetLogs(inner) => eth_getlogs(data, inner),
-        HEVMCalls::Rpc(inner) => rpc(data, inner),
+        HEVMCalls::EthGetLogs0(inner) => eth_getlogs(state, data, inner),
+        HEVMCalls::EthGetLogs1(inner) => eth_getlogs_at_fork(state, data, inner),
+        HEVMCalls::EthGetLogs2(inner) => eth_getlogs_at_endpoint(state, data, inner),
+        HEVMCalls::Rpc0(inner) => rpc(state, data, inner),
+        HEVMCalls::Rpc1(inner) => rpc_batch(state, data, inner),
+        HEVMCalls::Rpc2(inner) => rpc_at_fork(state, data, inner),
+        HEVMCalls::Rpc3(inner) => rpc_at_endpoint(state, data, inner),
         _ => return None,
     };
     Some(result)
@@ -295,41 +310,148 @@ fn create_fork_request<DB: DatabaseExt>(
     Ok(fork)
 }
 
-/// Retrieve the logs specified for the current fork.
-/// Equivalent to eth_getLogs but on a cheatcode.
-fn eth_getlogs<DB: DatabaseExt>(data: &EVMData<DB>, inner: &EthGetLogsCall) -> Result {
-    let url = data.db.active_fork_url().ok_or(fmt_err!("No active fork url found"))?;
-    if inner.0.to_alloy() > U256::from(u64::MAX) || inner.1.to_alloy() > U256::from(u64::MAX) {
-        return Err(fmt_err!("Blocks in block range must be less than 2^64 - 1"))
+/// Upper bound on how many times the requested block range may be bisected in response to a
+/// provider's range/result-limit error. Bounds the recursion so an endpoint that keeps rejecting
+/// ranges regardless of size can't send us into unbounded recursion.
+const MAX_GET_LOGS_SPLIT_DEPTH: u32 = 32;
+
+/// Best-effort detection of the "range too large" / "too many results" errors hosted RPC
+/// providers return when an `eth_getLogs` query spans more blocks or matches more logs than they
+/// allow. There's no standard error code for this, so we match on common substrings instead.
+fn is_get_logs_range_error<E: std::fmt::Display>(err: &E) -> bool {
+    let msg = err.to_string().to_lowercase();
+    [
+        "query returned more than",
+        "block range",
+        "range limit",
+        "range is too large",
+        "too many results",
+        "limit exceeded",
+        "exceeds the range",
+    ]
+    .iter()
+    .any(|needle| msg.contains(needle))
+}
+
+/// Fetches logs matching `filter` over `[from, to]`, automatically bisecting the range and
+/// retrying each half if the provider rejects the request for exceeding its block-range or
+/// result-count limit. Returns an error if a single block's worth of logs is already rejected.
+fn get_logs_chunked<M: Middleware>(
+    provider: &M,
+    filter: &Filter,
+    from: u64,
+    to: u64,
+    depth: u32,
+) -> Result<Vec<ethers::types::Log>> {
+    bisect_get_logs(from, to, depth, &mut |from, to| {
+        let ranged = filter.clone().from_block(from).to_block(to);
+        RuntimeOrHandle::new().block_on(provider.get_logs(&ranged)).map_err(|err| err.to_string())
+    })
+}
+
+/// Pure range-bisection driver behind [`get_logs_chunked`]: calls `fetch(from, to)` for the full
+/// range, and on a range/result-limit error recurses into `[from, mid]` and `[mid+1, to]`,
+/// concatenating the results. Kept free of any `Middleware`/provider dependency so the
+/// bisection and guard logic can be unit tested without a live or mocked provider.
+fn bisect_get_logs(
+    from: u64,
+    to: u64,
+    depth: u32,
+    fetch: &mut impl FnMut(u64, u64) -> std::result::Result<Vec<ethers::types::Log>, String>,
+) -> Result<Vec<ethers::types::Log>> {
+    if from > to {
+        return Err(fmt_err!("eth_getLogs: invalid block range [{from}, {to}] (`from` > `to`)"))
     }
-    // Cannot possibly have more than 4 topics in the topics array.
-    if inner.3.len() > 4 {
-        return Err(fmt_err!("Topics array must be less than 4 elements"))
+    match fetch(from, to) {
+        Ok(logs) => Ok(logs),
+        Err(err) if from == to => {
+            Err(fmt_err!("Error in calling eth_getLogs for block {from}: {err}"))
+        }
+        Err(err) if !is_get_logs_range_error(&err) => {
+            Err(fmt_err!("Error in calling eth_getLogs: {err}"))
+        }
+        Err(err) => {
+            if depth >= MAX_GET_LOGS_SPLIT_DEPTH {
+                return Err(fmt_err!(
+                    "eth_getLogs: block range [{from}, {to}] was still rejected ({err}) after \
+                     {depth} range splits"
+                ))
+            }
+            let mid = from + (to - from) / 2;
+            let mut logs = bisect_get_logs(from, mid, depth + 1, fetch)?;
+            logs.extend(bisect_get_logs(mid + 1, to, depth + 1, fetch)?);
+            Ok(logs)
+        }
     }
+}
 
-    let provider = ProviderBuilder::new(url).build()?;
-    let mut filter =
-        Filter::new().address(inner.2).from_block(inner.0.as_u64()).to_block(inner.1.as_u64());
-    for (i, item) in inner.3.iter().enumerate() {
-        match i {
-            0 => filter = filter.topic0(U256::from_be_bytes(item.to_owned()).to_ethers()),
-            1 => filter = filter.topic1(U256::from_be_bytes(item.to_owned()).to_ethers()),
-            2 => filter = filter.topic2(U256::from_be_bytes(item.to_owned()).to_ethers()),
-            3 => filter = filter.topic3(U256::from_be_bytes(item.to_owned()).to_ethers()),
-            _ => return Err(fmt_err!("Topics array should be less than 4 elements")),
-        };
+/// Directory (within the same per-chain, per-block cache directory that forked state caching
+/// already writes into, see [`create_fork_request`]) that memoized `eth_getLogs` responses are
+/// written to.
+const ETH_GET_LOGS_CACHE_SUBDIR: &str = "eth-get-logs";
+/// Directory that memoized `rpc` cheatcode responses are written to.
+const RPC_CACHE_SUBDIR: &str = "rpc-calls";
+
+/// Returns the on-disk path a cheatcode response keyed by `key` should be cached at, or `None`
+/// if storage caching is disabled for `url`. The active fork's current block number is folded
+/// into the cache directory (the same way forked state caching already keys on it), so the
+/// cache can never serve a response for a different chain state.
+///
+/// `key` is hashed with `keccak256` over its canonical JSON encoding rather than
+/// `std::hash::Hash`/`DefaultHasher`: the latter's algorithm is explicitly unstable across
+/// Rust toolchain versions, which would silently invalidate this cache (meant to be reproducible
+/// across CI runs and machines) on every compiler bump.
+fn rpc_response_cache_path<DB: DatabaseExt>(
+    state: &Cheatcodes,
+    data: &EVMData<DB>,
+    url: &str,
+    subdir: &str,
+    key: impl Serialize,
+) -> Option<PathBuf> {
+    if !state.config.rpc_storage_caching.enable_for_endpoint(url) {
+        return None
     }
+    let chain_id = data.env.cfg.chain_id.to::<u64>();
+    let block = data.env.block.number.to::<u64>();
+    let dir = Config::foundry_block_cache_dir(chain_id, block)?.join(subdir);
+
+    Some(dir.join(cache_response_filename(key)?))
+}
+
+/// Deterministically maps `key` to a cache filename via a `keccak256` hash of its canonical JSON
+/// encoding. Pulled out of [`rpc_response_cache_path`] so the hashing itself (`keccak256` rather
+/// than `DefaultHasher`, see there) is unit testable without an `EVMData`/`DatabaseExt` fixture.
+fn cache_response_filename(key: impl Serialize) -> Option<String> {
+    let key_json = serde_json::to_vec(&key).ok()?;
+    Some(format!("{:x}.json", keccak256(key_json)))
+}
 
-    let logs = RuntimeOrHandle::new()
-        .block_on(provider.get_logs(&filter))
-        .map_err(|_| fmt_err!("Error in calling eth_getLogs"))?;
+/// Best-effort read of a cached cheatcode response. Any I/O or deserialization failure is
+/// treated as a cache miss rather than an error.
+fn read_rpc_response_cache<T: DeserializeOwned>(path: &Path) -> Option<T> {
+    serde_json::from_str(&fs::read_to_string(path).ok()?).ok()
+}
+
+/// Best-effort write-through of a cheatcode response. Failing to persist the cache must never
+/// fail the cheatcode call itself.
+fn write_rpc_response_cache<T: Serialize>(path: &Path, value: &T) {
+    if let Some(parent) = path.parent() {
+        if fs::create_dir_all(parent).is_err() {
+            return
+        }
+    }
+    if let Ok(json) = serde_json::to_string(value) {
+        let _ = fs::write(path, json);
+    }
+}
 
+/// ABI-encodes the `Log`s returned from `eth_getLogs` into the cheatcode's return tuple.
+fn encode_logs(logs: &[ethers::types::Log]) -> Bytes {
     if logs.is_empty() {
-        let empty: Bytes = DynSolValue::Array(vec![]).encode_single().into();
-        return Ok(empty)
+        return DynSolValue::Array(vec![]).encode_single().into()
     }
 
-    let result = DynSolValue::Array(
+    DynSolValue::Array(
         logs.iter()
             .map(|entry| {
                 DynSolValue::Tuple(vec![
@@ -394,24 +516,613 @@ fn eth_getlogs<DB: DatabaseExt>(data: &EVMData<DB>, inner: &EthGetLogsCall) -> R
             .collect::<Vec<DynSolValue>>(),
     )
     .encode_single()
-    .into();
-    Ok(result)
+    .into()
 }
 
-fn rpc<DB: DatabaseExt>(data: &EVMData<DB>, inner: &RpcCall) -> Result {
-    let url = data.db.active_fork_url().ok_or(fmt_err!("No active fork url found"))?;
+/// Either a fork id or a configured RPC alias/URL that `rpc`/`eth_getLogs` may target directly,
+/// without selecting the corresponding fork as active.
+enum RpcTarget {
+    ForkId(U256),
+    UrlOrAlias(String),
+}
+
+/// Resolves the RPC endpoint for `target` via the fork registry (for a fork id) or the project
+/// config (for a URL/alias), without mutating which fork is currently active.
+fn resolve_rpc_target<DB: DatabaseExt>(
+    state: &Cheatcodes,
+    data: &EVMData<DB>,
+    target: RpcTarget,
+) -> Result<String> {
+    match target {
+        RpcTarget::ForkId(id) => data
+            .db
+            .get_fork_url(id)
+            .ok_or_else(|| fmt_err!("No RPC endpoint found for fork id {id}")),
+        RpcTarget::UrlOrAlias(url_or_alias) => resolve_rpc_alias(&state.config, url_or_alias),
+    }
+}
+
+/// Resolves `url_or_alias` to an RPC endpoint via the project config. Doesn't take `EVMData` at
+/// all, so unlike the `ForkId` branch of `resolve_rpc_target`, it can't touch fork state even by
+/// accident — the active fork is never selected, read, or mutated by this path.
+fn resolve_rpc_alias(config: &Config, url_or_alias: String) -> Result<String> {
+    config.get_rpc_url(url_or_alias)
+}
+
+/// Retrieve the logs specified by `from_block..=to_block` from `url`.
+/// Equivalent to eth_getLogs but on a cheatcode.
+fn eth_getlogs_inner<DB: DatabaseExt>(
+    state: &Cheatcodes,
+    data: &EVMData<DB>,
+    url: String,
+    from_block: ethers::types::U256,
+    to_block: ethers::types::U256,
+    target: ethers::types::Address,
+    topics: &[[u8; 32]],
+    // Whether `url` is the currently active fork, i.e. whether `data.env`'s chain id and block
+    // number actually describe `url`'s state. Cross-fork/cross-endpoint calls must not cache:
+    // the active fork's pinned block says nothing about a *different* endpoint's chain state,
+    // so keying on it would serve stale responses forever as that other chain progresses.
+    is_active_fork: bool,
+) -> Result {
+    if from_block.to_alloy() > U256::from(u64::MAX) || to_block.to_alloy() > U256::from(u64::MAX) {
+        return Err(fmt_err!("Blocks in block range must be less than 2^64 - 1"))
+    }
+    if from_block.to_alloy() > to_block.to_alloy() {
+        return Err(fmt_err!("`fromBlock` must not be greater than `toBlock`"))
+    }
+    // Cannot possibly have more than 4 topics in the topics array.
+    if topics.len() > 4 {
+        return Err(fmt_err!("Topics array must have at most 4 elements"))
+    }
+
+    let from = from_block.as_u64();
+    let to = to_block.as_u64();
+    let cache_key = (url.as_str(), from, to, target, topics.to_vec());
+    let cache_path = is_active_fork
+        .then(|| rpc_response_cache_path(state, data, &url, ETH_GET_LOGS_CACHE_SUBDIR, cache_key))
+        .flatten();
+    if let Some(path) = &cache_path {
+        if let Some(logs) = read_rpc_response_cache::<Vec<ethers::types::Log>>(path) {
+            return Ok(encode_logs(&logs))
+        }
+    }
+
     let provider = ProviderBuilder::new(url).build()?;
+    let mut filter = Filter::new().address(target);
+    for (i, item) in topics.iter().enumerate() {
+        match i {
+            0 => filter = filter.topic0(U256::from_be_bytes(item.to_owned()).to_ethers()),
+            1 => filter = filter.topic1(U256::from_be_bytes(item.to_owned()).to_ethers()),
+            2 => filter = filter.topic2(U256::from_be_bytes(item.to_owned()).to_ethers()),
+            3 => filter = filter.topic3(U256::from_be_bytes(item.to_owned()).to_ethers()),
+            _ => return Err(fmt_err!("Topics array must have at most 4 elements")),
+        };
+    }
+
+    let mut logs = get_logs_chunked(&provider, &filter, from, to, 0)?;
+    // The provider is expected to return logs in ascending order already, but range splitting
+    // means we've issued multiple independent requests, so re-establish a deterministic order.
+    logs.sort_by_key(|log| {
+        (log.block_number.unwrap_or_default(), log.log_index.unwrap_or_default())
+    });
+
+    if let Some(path) = &cache_path {
+        write_rpc_response_cache(path, &logs);
+    }
+
+    Ok(encode_logs(&logs))
+}
 
-    let method = inner.0.as_str();
-    let params = inner.1.as_str();
+/// `eth_getLogs` against the currently active fork.
+fn eth_getlogs<DB: DatabaseExt>(
+    state: &Cheatcodes,
+    data: &EVMData<DB>,
+    inner: &EthGetLogs0Call,
+) -> Result {
+    let url = data.db.active_fork_url().ok_or(fmt_err!("No active fork url found"))?;
+    eth_getlogs_inner(state, data, url, inner.0, inner.1, inner.2, &inner.3, true)
+}
+
+/// `eth_getLogs` against the fork identified by `forkId`, without selecting it.
+fn eth_getlogs_at_fork<DB: DatabaseExt>(
+    state: &Cheatcodes,
+    data: &EVMData<DB>,
+    inner: &EthGetLogs1Call,
+) -> Result {
+    let url = resolve_rpc_target(state, data, RpcTarget::ForkId(inner.0.to_alloy()))?;
+    eth_getlogs_inner(state, data, url, inner.1, inner.2, inner.3, &inner.4, false)
+}
+
+/// `eth_getLogs` against the endpoint identified by a URL or configured alias, without
+/// selecting or creating a fork for it.
+fn eth_getlogs_at_endpoint<DB: DatabaseExt>(
+    state: &Cheatcodes,
+    data: &EVMData<DB>,
+    inner: &EthGetLogs2Call,
+) -> Result {
+    let url = resolve_rpc_target(state, data, RpcTarget::UrlOrAlias(inner.0.clone()))?;
+    eth_getlogs_inner(state, data, url, inner.1, inner.2, inner.3, &inner.4, false)
+}
+
+/// JSON-RPC methods whose response is safe to memoize: deterministic, read-only calls whose
+/// result depends only on the chain state already pinned by the cache directory (chain id +
+/// block number), not on wall-clock time, mempool contents, or node-local state. Anything not
+/// listed here — state-mutating calls like `eth_sendRawTransaction`/`eth_sendTransaction`, or
+/// non-deterministic reads like `eth_blockNumber`/`eth_gasPrice` — must never be cached: a cache
+/// hit on a mutating call would silently skip the call itself, leaving a test believing it
+/// broadcast a transaction that never happened.
+const CACHEABLE_RPC_METHODS: &[&str] = &[
+    "eth_call",
+    "eth_getBalance",
+    "eth_getCode",
+    "eth_getStorageAt",
+    "eth_getTransactionByHash",
+    "eth_getTransactionByBlockHashAndIndex",
+    "eth_getTransactionByBlockNumberAndIndex",
+    "eth_getTransactionReceipt",
+    "eth_getTransactionCount",
+    "eth_getBlockByHash",
+    "eth_getBlockByNumber",
+    "eth_getBlockTransactionCountByHash",
+    "eth_getBlockTransactionCountByNumber",
+    "eth_getUncleByBlockHashAndIndex",
+    "eth_getUncleByBlockNumberAndIndex",
+    "eth_getUncleCountByBlockHash",
+    "eth_getUncleCountByBlockNumber",
+    "eth_getLogs",
+    "eth_getProof",
+    "eth_chainId",
+    "net_version",
+];
+
+/// Whether `method`'s response may be cached by [`rpc_call`]. See [`CACHEABLE_RPC_METHODS`].
+fn is_cacheable_rpc_method(method: &str) -> bool {
+    CACHEABLE_RPC_METHODS.contains(&method)
+}
+
+/// Performs a single `rpc` call against `url`.
+fn rpc_call<DB: DatabaseExt>(
+    state: &Cheatcodes,
+    data: &EVMData<DB>,
+    url: String,
+    method: &str,
+    params: &str,
+    // See the identically-named parameter on `eth_getlogs_inner` for why this gates caching.
+    is_active_fork: bool,
+) -> Result {
     let params_json: Value = serde_json::from_str(params)?;
 
-    let result: Value = RuntimeOrHandle::new()
-        .block_on(provider.request(method, params_json))
-        .map_err(|err| fmt_err!("Error in calling {:?}: {:?}", method, err))?;
+    let cache_key = (url.as_str(), method, params);
+    let cache_path = (is_active_fork && is_cacheable_rpc_method(method))
+        .then(|| rpc_response_cache_path(state, data, &url, RPC_CACHE_SUBDIR, cache_key))
+        .flatten();
+    let result: Value = if let Some(cached) =
+        cache_path.as_deref().and_then(read_rpc_response_cache)
+    {
+        cached
+    } else {
+        let provider = ProviderBuilder::new(url).build()?;
+        let result: Value = RuntimeOrHandle::new()
+            .block_on(provider.request(method, params_json))
+            .map_err(|err| fmt_err!("Error in calling {:?}: {:?}", method, err))?;
+        if let Some(path) = &cache_path {
+            write_rpc_response_cache(path, &result);
+        }
+        result
+    };
 
     let result_as_tokens =
         value_to_token(&result).map_err(|err| fmt_err!("Failed to parse result: {err}"))?;
 
     Ok(result_as_tokens.encode_single().into())
 }
+
+/// `rpc` against the currently active fork.
+fn rpc<DB: DatabaseExt>(state: &Cheatcodes, data: &EVMData<DB>, inner: &Rpc0Call) -> Result {
+    let url = data.db.active_fork_url().ok_or(fmt_err!("No active fork url found"))?;
+    rpc_call(state, data, url, inner.0.as_str(), inner.1.as_str(), true)
+}
+
+/// `rpc` against the fork identified by `forkId`, without selecting it.
+fn rpc_at_fork<DB: DatabaseExt>(
+    state: &Cheatcodes,
+    data: &EVMData<DB>,
+    inner: &Rpc2Call,
+) -> Result {
+    let url = resolve_rpc_target(state, data, RpcTarget::ForkId(inner.0.to_alloy()))?;
+    rpc_call(state, data, url, inner.1.as_str(), inner.2.as_str(), false)
+}
+
+/// `rpc` against the endpoint identified by a URL or configured alias, without selecting or
+/// creating a fork for it.
+fn rpc_at_endpoint<DB: DatabaseExt>(
+    state: &Cheatcodes,
+    data: &EVMData<DB>,
+    inner: &Rpc3Call,
+) -> Result {
+    let url = resolve_rpc_target(state, data, RpcTarget::UrlOrAlias(inner.0.clone()))?;
+    rpc_call(state, data, url, inner.1.as_str(), inner.2.as_str(), false)
+}
+
+/// How many times [`send_rpc_batch`] retries a transient (network or HTTP 5xx/429) failure of the
+/// whole batch POST before giving up.
+///
+/// `Middleware`/`JsonRpcClient` — the abstraction every other call in this file goes through —
+/// has no way to express "one HTTP request, many JSON-RPC calls"; it's strictly one call in, one
+/// response out. A real batch has to bypass it and talk JSON-RPC 2.0 batching directly, which
+/// also means it loses the retry/backoff a `ProviderBuilder`-built provider applies to individual
+/// calls. This retry loop approximates that resilience for the batch path instead of silently
+/// dropping it.
+const RPC_BATCH_MAX_RETRIES: u32 = 3;
+
+/// Sends every `(method, params)` pair in `methods`/`params` as a single JSON-RPC 2.0 batch
+/// request — one HTTP round-trip for the whole batch, collapsing what would otherwise be N
+/// sequential (or N concurrent) round-trips into one — and returns each entry's decoded result in
+/// request order.
+///
+/// `url` is still validated by building a `ProviderBuilder` provider for it first, the same way
+/// every other call path in this file validates its endpoint. That provider is then discarded:
+/// the batch itself is posted to `url` as given with a plain `reqwest::Client`, because JSON-RPC
+/// batching is a wire-level concern `Middleware::request` can't express, so there's no provider
+/// call to route it through.
+fn send_rpc_batch(url: &str, methods: &[String], params: &[Value]) -> Result<Vec<Value>> {
+    if methods.is_empty() {
+        return Ok(Vec::new())
+    }
+    // Validates `url` the same way every other call path in this file does; the provider this
+    // builds is discarded and the batch below is posted to `url` unchanged (see the doc comment
+    // above).
+    let _ = ProviderBuilder::new(url).build()?;
+
+    let batch: Vec<Value> = methods
+        .iter()
+        .zip(params)
+        .enumerate()
+        .map(|(id, (method, params))| {
+            serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "method": method,
+                "params": params,
+            })
+        })
+        .collect();
+
+    let client = reqwest::Client::new();
+    let mut responses: Vec<Value> = RuntimeOrHandle::new().block_on(async {
+        let mut attempt = 0;
+        loop {
+            match send_rpc_batch_once(&client, url, &batch).await {
+                Ok(responses) => break Ok(responses),
+                Err(BatchSendError::Retryable(_)) if attempt < RPC_BATCH_MAX_RETRIES => {
+                    attempt += 1;
+                    tokio::time::sleep(Duration::from_millis(200 * attempt as u64)).await;
+                }
+                Err(BatchSendError::Retryable(err) | BatchSendError::Fatal(err)) => break Err(err),
+            }
+        }
+    })?;
+
+    correlate_batch_by_id(methods, &mut responses)
+}
+
+/// Distinguishes a batch POST failure worth retrying (network error, server overload) from one
+/// that will never succeed (the endpoint rejected the request outright, or replied with something
+/// that isn't a JSON-RPC batch response) so [`send_rpc_batch`]'s retry loop doesn't burn attempts
+/// and backoff time on a permanent failure.
+enum BatchSendError {
+    Retryable(Error),
+    Fatal(Error),
+}
+
+/// Performs a single attempt at POSTing `batch` to `url`. Split out of [`send_rpc_batch`] so the
+/// retry loop there can call it repeatedly without re-nesting async blocks.
+async fn send_rpc_batch_once(
+    client: &reqwest::Client,
+    url: &str,
+    batch: &[Value],
+) -> std::result::Result<Vec<Value>, BatchSendError> {
+    let response = client
+        .post(url)
+        .json(&batch)
+        .send()
+        .await
+        .map_err(|err| BatchSendError::Retryable(err.into()))?;
+
+    let status = response.status();
+    if status.is_server_error() || status.as_u16() == 429 {
+        return Err(BatchSendError::Retryable(fmt_err!(
+            "batched rpc call got a retryable status: {status}"
+        )))
+    }
+    if !status.is_success() {
+        return Err(BatchSendError::Fatal(fmt_err!(
+            "batched rpc call failed with status: {status}"
+        )))
+    }
+
+    response.json().await.map_err(|err| BatchSendError::Fatal(err.into()))
+}
+
+/// Correlates raw JSON-RPC batch response objects back to the call that produced them by the
+/// numeric `id` [`send_rpc_batch`] assigned to each entry in the request (responses are not
+/// guaranteed to come back in request order per the JSON-RPC 2.0 spec), and returns each result
+/// in request order. Extracted from [`send_rpc_batch`] so the correlation/error-propagation logic
+/// is unit testable without a live batch endpoint.
+fn correlate_batch_by_id(methods: &[String], responses: &mut [Value]) -> Result<Vec<Value>> {
+    let mut by_id: HashMap<u64, Value> = HashMap::with_capacity(responses.len());
+    for response in responses.iter_mut() {
+        let id = response
+            .get("id")
+            .and_then(Value::as_u64)
+            .ok_or_else(|| fmt_err!("batched rpc response is missing a numeric `id`"))?;
+        by_id.insert(id, response.take());
+    }
+
+    (0..methods.len() as u64)
+        .map(|id| {
+            let response = by_id
+                .remove(&id)
+                .ok_or_else(|| fmt_err!("missing batched rpc response for id {id}"))?;
+            if let Some(error) = response.get("error") {
+                return Err(fmt_err!(
+                    "rpc call {:?} failed: {error}",
+                    methods[id as usize]
+                ))
+            }
+            response.get("result").cloned().ok_or_else(|| {
+                fmt_err!(
+                    "rpc call {:?} response had neither `result` nor `error`",
+                    methods[id as usize]
+                )
+            })
+        })
+        .collect()
+}
+
+/// Batched variant of `rpc`: sends every `(method, params)` pair against the active fork in a
+/// single JSON-RPC batch request, except for entries [`is_cacheable_rpc_method`] already has a
+/// cached response for on disk (see `rpc_call`), which are served from the cache without ever
+/// joining the network batch. Newly-fetched cacheable entries are written through to the cache
+/// the same way `rpc_call` does, so switching a fork test from individual `rpc` calls to this
+/// batched overload doesn't lose the "runs fully offline against a pinned fork block" property.
+fn rpc_batch<DB: DatabaseExt>(state: &Cheatcodes, data: &EVMData<DB>, inner: &Rpc1Call) -> Result {
+    let url = data.db.active_fork_url().ok_or(fmt_err!("No active fork url found"))?;
+    let (methods, params) = (&inner.0, &inner.1);
+    if methods.len() != params.len() {
+        return Err(fmt_err!(
+            "`rpc` batch calls require `methods` and `params` to have the same length"
+        ))
+    }
+
+    let params_json = params
+        .iter()
+        .map(|p| serde_json::from_str(p))
+        .collect::<serde_json::Result<Vec<Value>>>()?;
+
+    let mut results: Vec<Option<Value>> = vec![None; methods.len()];
+    let mut cache_paths: Vec<Option<PathBuf>> = vec![None; methods.len()];
+    for (i, method) in methods.iter().enumerate() {
+        if !is_cacheable_rpc_method(method) {
+            continue
+        }
+        // Keyed on the raw params string, not the parsed `Value`, so this lines up with
+        // `rpc_call`'s cache key (see its `cache_key` above) and the two paths share entries for
+        // the same logical call instead of hashing to different files.
+        let cache_key = (url.as_str(), method.as_str(), params[i].as_str());
+        let path = rpc_response_cache_path(state, data, &url, RPC_CACHE_SUBDIR, cache_key);
+        if let Some(cached) = path.as_deref().and_then(read_rpc_response_cache) {
+            results[i] = Some(cached);
+        } else {
+            cache_paths[i] = path;
+        }
+    }
+
+    let misses: Vec<usize> = (0..methods.len()).filter(|&i| results[i].is_none()).collect();
+    if !misses.is_empty() {
+        let miss_methods: Vec<String> = misses.iter().map(|&i| methods[i].clone()).collect();
+        let miss_params: Vec<Value> = misses.iter().map(|&i| params_json[i].clone()).collect();
+        let fetched = send_rpc_batch(&url, &miss_methods, &miss_params)?;
+        for (&i, value) in misses.iter().zip(fetched) {
+            if let Some(path) = &cache_paths[i] {
+                write_rpc_response_cache(path, &value);
+            }
+            results[i] = Some(value);
+        }
+    }
+
+    let tokens = results
+        .into_iter()
+        .map(|result| {
+            let result = result.expect("every batch entry is either cached or freshly fetched");
+            value_to_token(&result).map_err(|err| fmt_err!("Failed to parse result: {err}"))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(DynSolValue::Array(tokens).encode_single().into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn log(block: u64, index: u64) -> ethers::types::Log {
+        let mut log = ethers::types::Log::default();
+        log.block_number = Some(block.into());
+        log.log_index = Some(index.into());
+        log
+    }
+
+    #[test]
+    fn bisects_on_range_limit_error_and_preserves_order() {
+        let mut calls = Vec::new();
+        let result = bisect_get_logs(0, 3, 0, &mut |from, to| {
+            calls.push((from, to));
+            if from == 0 && to == 3 {
+                Err("query returned more than 10000 results".to_string())
+            } else {
+                Ok(vec![log(from, 0), log(to, 0)])
+            }
+        })
+        .unwrap();
+
+        assert_eq!(calls, vec![(0, 3), (0, 1), (2, 3)]);
+        assert_eq!(result.len(), 4);
+        let blocks: Vec<u64> =
+            result.iter().map(|log| log.block_number.unwrap().as_u64()).collect();
+        assert_eq!(blocks, vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn gives_up_after_a_single_block_is_still_rejected() {
+        let result = bisect_get_logs(5, 5, 0, &mut |_, _| {
+            Err("query returned more than 10000 results".to_string())
+        });
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn non_range_errors_are_not_bisected() {
+        let mut calls = 0;
+        let result = bisect_get_logs(0, 10, 0, &mut |_, _| {
+            calls += 1;
+            Err("execution reverted".to_string())
+        });
+        assert!(result.is_err());
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    fn rejects_inverted_block_range() {
+        let result = bisect_get_logs(10, 5, 0, &mut |_, _| Ok(vec![]));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn respects_the_split_depth_guard() {
+        let mut calls = 0;
+        // Start one level below the cap so the guard trips on the second fetch. From depth 0 a
+        // 1,000,000-block range reaches a single block after about 20 splits, so the
+        // single-block error would end the recursion before the guard could ever trip.
+        let result = bisect_get_logs(0, 1_000_000, MAX_GET_LOGS_SPLIT_DEPTH - 1, &mut |_, _| {
+            calls += 1;
+            Err("block range is too large".to_string())
+        });
+        assert!(result.is_err());
+        assert!(calls <= 3);
+    }
+
+    #[test]
+    fn only_allow_listed_methods_are_cacheable() {
+        assert!(is_cacheable_rpc_method("eth_call"));
+        assert!(is_cacheable_rpc_method("eth_getLogs"));
+        assert!(!is_cacheable_rpc_method("eth_sendRawTransaction"));
+        assert!(!is_cacheable_rpc_method("eth_sendTransaction"));
+        assert!(!is_cacheable_rpc_method("eth_blockNumber"));
+        assert!(!is_cacheable_rpc_method("eth_gasPrice"));
+        assert!(!is_cacheable_rpc_method("eth_accounts"));
+        assert!(!is_cacheable_rpc_method("personal_sign"));
+    }
+
+    #[test]
+    fn cache_round_trips_through_disk() {
+        let dir = std::env::temp_dir().join(format!(
+            "foundry-rpc-cache-test-{:x}",
+            keccak256(b"cache_round_trips_through_disk")
+        ));
+        let path = dir.join("response.json");
+
+        assert!(read_rpc_response_cache::<Value>(&path).is_none());
+
+        let value = serde_json::json!({"result": "0x1"});
+        write_rpc_response_cache(&path, &value);
+
+        let cached: Value = read_rpc_response_cache(&path).expect("cache write should round-trip");
+        assert_eq!(cached, value);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn cache_filename_is_deterministic_and_key_sensitive() {
+        // Guards the use of `keccak256` over `DefaultHasher`: `rpc_response_cache_path`'s filename
+        // must depend only on `key`'s contents (not process-local randomization or toolchain
+        // hasher behavior) and must still distinguish different keys.
+        let key = ("https://example.com", "eth_call", r#"["0x0"]"#);
+        let other_key = ("https://example.com", "eth_call", r#"["0x1"]"#);
+
+        let a = cache_response_filename(key).unwrap();
+        let b = cache_response_filename(key).unwrap();
+        let c = cache_response_filename(other_key).unwrap();
+
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+    }
+
+    #[test]
+    fn correlates_batch_results_by_id_regardless_of_response_order() {
+        let methods = vec!["eth_chainId".to_string(), "eth_blockNumber".to_string()];
+        // Responses deliberately come back out of order, which the JSON-RPC 2.0 spec allows.
+        let mut responses = vec![
+            serde_json::json!({"jsonrpc": "2.0", "id": 1, "result": "0x10"}),
+            serde_json::json!({"jsonrpc": "2.0", "id": 0, "result": "0x1"}),
+        ];
+
+        let results = correlate_batch_by_id(&methods, &mut responses).unwrap();
+
+        assert_eq!(results, vec![serde_json::json!("0x1"), serde_json::json!("0x10")]);
+    }
+
+    #[test]
+    fn a_failed_call_is_reported_against_its_own_method() {
+        let methods = vec![
+            "eth_chainId".to_string(),
+            "eth_blockNumber".to_string(),
+            "eth_gasPrice".to_string(),
+        ];
+        let mut responses = vec![
+            serde_json::json!({"jsonrpc": "2.0", "id": 0, "result": "0x1"}),
+            serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "error": {"code": -32000, "message": "connection reset"}
+            }),
+            serde_json::json!({"jsonrpc": "2.0", "id": 2, "result": "0x3"}),
+        ];
+
+        let err = correlate_batch_by_id(&methods, &mut responses).unwrap_err();
+        assert!(err.to_string().contains("eth_blockNumber"));
+        assert!(err.to_string().contains("connection reset"));
+    }
+
+    #[test]
+    fn missing_response_id_is_reported() {
+        let methods = vec!["eth_chainId".to_string(), "eth_blockNumber".to_string()];
+        let mut responses = vec![serde_json::json!({"jsonrpc": "2.0", "id": 0, "result": "0x1"})];
+
+        let err = correlate_batch_by_id(&methods, &mut responses).unwrap_err();
+        assert!(err.to_string().contains("id 1"));
+    }
+
+    #[test]
+    fn response_missing_both_result_and_error_is_reported() {
+        let methods = vec!["eth_chainId".to_string()];
+        let mut responses = vec![serde_json::json!({"jsonrpc": "2.0", "id": 0})];
+
+        let err = correlate_batch_by_id(&methods, &mut responses).unwrap_err();
+        assert!(err.to_string().contains("eth_chainId"));
+        assert!(err.to_string().contains("neither"));
+    }
+
+    #[test]
+    fn url_or_alias_targeting_cannot_touch_fork_state() {
+        // `resolve_rpc_alias` doesn't take `EVMData`/`DatabaseExt` at all, so this test (and the
+        // type signature itself) proves the `UrlOrAlias` path of `resolve_rpc_target` can't
+        // select or mutate the active fork, unlike the `ForkId` path which necessarily goes
+        // through `data.db`.
+        let url = "https://example.com/rpc";
+        let resolved = resolve_rpc_alias(&Config::default(), url.to_string()).unwrap();
+        assert_eq!(resolved, url);
+    }
+}